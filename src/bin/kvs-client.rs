@@ -0,0 +1,55 @@
+use kvs::{read_framed, write_framed, Response};
+use std::io::{BufReader, BufWriter};
+use std::net::TcpStream;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Opt {
+	#[structopt(long, default_value = "127.0.0.1:4000")]
+	addr: String,
+	#[structopt(subcommand)]
+	cmd: CliCommand,
+}
+
+#[derive(StructOpt)]
+enum CliCommand {
+	/// Get the string value of a given string key
+	Get { key: String },
+	/// Set the value of a string key to a string
+	Set { key: String, value: String },
+	/// Remove a given key
+	#[structopt(name = "rm")]
+	Remove { key: String },
+}
+
+fn main() -> kvs::Result<()> {
+	let opt = Opt::from_args();
+	let stream = TcpStream::connect(&opt.addr)?;
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut writer = BufWriter::new(stream);
+
+	let req = match opt.cmd {
+		CliCommand::Get { key } => kvs::Request::Get(key),
+		CliCommand::Set { key, value } => kvs::Request::Set(key, value),
+		CliCommand::Remove { key } => kvs::Request::Remove(key),
+	};
+
+	write_framed(&mut writer, &req)?;
+
+	match read_framed(&mut reader)? {
+		Response::Value(value) => {
+			if !value.is_empty() {
+				println!("{}", value);
+			}
+			Ok(())
+		}
+		Response::NotFound => {
+			println!("Key not found");
+			Ok(())
+		}
+		Response::Err(msg) => {
+			println!("{}", msg);
+			Err(kvs::KvsError::Unexpected)
+		}
+	}
+}
@@ -0,0 +1,34 @@
+use kvs::{read_framed, write_framed, KvStore, Request};
+use std::io::{BufReader, BufWriter};
+use std::net::{TcpListener, TcpStream};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Opt {
+	#[structopt(long, default_value = "127.0.0.1:4000")]
+	addr: String,
+}
+
+fn main() -> kvs::Result<()> {
+	let opt = Opt::from_args();
+	let mut kvs = KvStore::open(std::env::current_dir()?)?;
+	let listener = TcpListener::bind(&opt.addr)?;
+
+	for stream in listener.incoming() {
+		handle_conn(stream?, &mut kvs)?;
+	}
+
+	Ok(())
+}
+
+fn handle_conn(stream: TcpStream, kvs: &mut KvStore) -> kvs::Result<()> {
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut writer = BufWriter::new(stream);
+
+	while let Ok(req) = read_framed::<_, Request>(&mut reader) {
+		let resp = kvs.handle_request(req);
+		write_framed(&mut writer, &resp)?;
+	}
+
+	Ok(())
+}
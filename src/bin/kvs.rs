@@ -16,6 +16,10 @@ enum CliCommand {
 	/// Remove a given key
 	#[structopt(name = "rm")]
 	Remove { key: String },
+	/// Force compaction of the on-disk log
+	Compact,
+	/// Print index and on-disk log statistics
+	Stats,
 }
 
 fn main() -> kvs::Result<()> {
@@ -42,6 +46,20 @@ fn main() -> kvs::Result<()> {
 				Err(e)
 			}
 		},
+		Some(CliCommand::Compact) => kvs.compact(),
+		Some(CliCommand::Stats) => match kvs.stats() {
+			Ok(stats) => {
+				println!("live keys: {}", stats.live_keys);
+				println!("generations: {}", stats.generations);
+				println!("total bytes: {}", stats.total_bytes);
+				println!("live bytes: {}", stats.live_bytes);
+				println!("dead bytes: {}", stats.dead_bytes);
+				println!("dead ratio: {:.2}%", stats.dead_ratio * 100.0);
+				println!("lossy generations: {}", stats.lossy_generations);
+				Ok(())
+			}
+			Err(e) => Err(e),
+		},
 		None => unimplemented!(),
 	}
 }
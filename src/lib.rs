@@ -1,12 +1,16 @@
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "compress")]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use memmap::Mmap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
-use std::fs::{create_dir_all, read_dir, remove_file, File};
-use std::io::{prelude::*, BufReader, BufWriter, SeekFrom};
+use std::fs::{create_dir_all, metadata, read_dir, remove_file, File};
+use std::io::{prelude::*, BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 
 const LOG_FILE_EXT: &str = "log";
+const HINT_FILE_EXT: &str = "hint";
 const COMPACTION_THRESHOLD: u64 = 1024 * 1024;
 
 #[derive(Debug)]
@@ -48,9 +52,117 @@ type Gen = u64;
 struct IndexMeta {
     gen: Gen,
     pos: u64,
+    len: u64,
 }
 
-type KvsReaders = HashMap<Gen, BufReader<File>>;
+/// One entry of a generation's `.hint` file.
+#[derive(Serialize, Deserialize, Debug)]
+struct HintRecord {
+    key: KvsKey,
+    pos: u64,
+    len: u64,
+}
+
+/// Compression codec applied to a generation's log by `compact()`. The
+/// active, still-appending generation is always written as `None` so
+/// appends stay cheap; only finalized (compacted) generations may use a
+/// real codec, behind the `compress` feature flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    None,
+    #[cfg(feature = "compress")]
+    Gzip,
+}
+
+impl Codec {
+    fn to_byte(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            #[cfg(feature = "compress")]
+            Codec::Gzip => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Codec {
+        match byte {
+            #[cfg(feature = "compress")]
+            1 => Codec::Gzip,
+            _ => Codec::None,
+        }
+    }
+
+    fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "compress")]
+            Codec::Gzip => {
+                let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+                enc.write_all(bytes)?;
+                Ok(enc.finish()?)
+            }
+        }
+    }
+
+    fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "compress")]
+            Codec::Gzip => {
+                let mut buf = Vec::new();
+                GzDecoder::new(bytes).read_to_end(&mut buf)?;
+                Ok(buf)
+            }
+        }
+    }
+}
+
+/// How a `KvStore` is opened: currently just the codec new compactions
+/// should use. `KvStore::open` uses the default (no compression).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KvStoreConfig {
+    pub codec: Codec,
+}
+
+/// A snapshot of a store's on-disk footprint, for deciding whether
+/// `compact()` is worth running instead of waiting on the fixed
+/// `COMPACTION_THRESHOLD`.
+#[derive(Debug)]
+pub struct KvStoreStats {
+    pub live_keys: usize,
+    pub generations: usize,
+    pub total_bytes: u64,
+    pub live_bytes: u64,
+    pub dead_bytes: u64,
+    pub dead_ratio: f64,
+    /// Generations `open` couldn't recover keys from: compacted with a real
+    /// codec, then lost their `.hint` file before this open.
+    pub lossy_generations: usize,
+}
+
+/// A mapped generation log plus the codec its records were written with.
+/// `codec` is read back from the 1-byte header that `compact()` writes at
+/// the start of every compacted generation; a generation with no `.hint`
+/// file is the still-growing active one and has no header at all.
+#[derive(Debug)]
+struct GenReader {
+    mmap: Option<Mmap>,
+    codec: Codec,
+}
+
+impl GenReader {
+    /// `Mmap::map` refuses zero-length files, so a fresh active generation
+    /// (or a compacted one that happened to end up empty) has no mapping at
+    /// all; treat that the same as a mapping with no bytes.
+    fn bytes(&self) -> &[u8] {
+        match &self.mmap {
+            Some(mmap) => &mmap[..],
+            None => &[],
+        }
+    }
+}
+
+type KvsReaders = HashMap<Gen, GenReader>;
 
 type KvsIndex = HashMap<KvsKey, IndexMeta>;
 
@@ -60,6 +172,42 @@ pub enum KvsCommand {
     Remove(KvsKey),
 }
 
+/// A request sent over the wire by `kvs-client`, mirroring the `CliCommand`
+/// subcommands but carrying no addr/formatting concerns of its own.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Request {
+    Get(KvsKey),
+    Set(KvsKey, KvsValue),
+    Remove(KvsKey),
+}
+
+/// The response `kvs-server` sends back for a `Request`.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Response {
+    Value(KvsValue),
+    NotFound,
+    Err(String),
+}
+
+/// Write `value` to `writer` as a 4-byte big-endian length prefix followed by
+/// its JSON encoding, so a single connection can carry multiple requests.
+pub fn write_framed<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<()> {
+    let body = serde_json::to_vec(value)?;
+    writer.write_all(&(body.len() as u32).to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read one length-prefixed JSON value written by `write_framed`.
+pub fn read_framed<R: Read, T: DeserializeOwned>(reader: &mut R) -> Result<T> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let mut body = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+    reader.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
 #[derive(Debug)]
 pub struct KvStore {
     index: KvsIndex,
@@ -68,10 +216,16 @@ pub struct KvStore {
     gen: Gen,
     cursor: u64,
     path: PathBuf,
+    codec: Codec,
+    lossy_generations: usize,
 }
 
 impl KvStore {
     pub fn open(path: impl Into<PathBuf>) -> Result<KvStore> {
+        KvStore::open_with_config(path, KvStoreConfig::default())
+    }
+
+    pub fn open_with_config(path: impl Into<PathBuf>, config: KvStoreConfig) -> Result<KvStore> {
         let path = path.into();
 
         create_dir_all(&path)?;
@@ -81,32 +235,35 @@ impl KvStore {
         }
 
         let mut gen_list = gen_list(&path)?;
-        let mut readers = readers(&path, &gen_list)?;
+        let readers = readers(&path, &gen_list)?;
         gen_list.sort();
         let gen = match gen_list.last() {
             Some(last) => last + 1,
             None => 0,
         };
-        let writer = prepare_new_gen(&path, gen, &mut readers)?;
+        let writer = prepare_new_gen(&path, gen)?;
+        let (index, lossy_generations) = index(&path, &gen_list)?;
 
         Ok(KvStore {
-            index: index(&path, &gen_list)?,
+            index,
             readers,
             writer,
             cursor: 0,
             gen,
             path,
+            codec: config.codec,
+            lossy_generations,
         })
     }
 
-    pub fn get(&mut self, key: KvsKey) -> Result<Option<KvsValue>> {
+    pub fn get(&self, key: KvsKey) -> Result<Option<KvsValue>> {
         match self.index.get(&key) {
-            Some(IndexMeta { gen, pos }) => match self.readers.get_mut(&gen) {
+            Some(IndexMeta { gen, pos, len }) => match self.readers.get(&gen) {
                 Some(reader) => {
-                    reader.seek(SeekFrom::Start(*pos))?;
-                    let mut cmd = String::new();
-                    reader.read_line(&mut cmd)?;
-                    match serde_json::from_str(&cmd)? {
+                    let start = *pos as usize;
+                    let end = start + *len as usize;
+                    let bytes = reader.codec.decompress(&reader.bytes()[start..end])?;
+                    match serde_json::from_slice(&bytes)? {
                         KvsCommand::Set(_, val) => Ok(Some(val)),
                         _ => panic!(),
                     }
@@ -122,6 +279,7 @@ impl KvStore {
         let idx_val = IndexMeta {
             gen: self.gen,
             pos: self.cursor,
+            len: set_cmd.len() as u64,
         };
 
         self.log_cmd(set_cmd)?;
@@ -150,6 +308,56 @@ impl KvStore {
         }
     }
 
+    /// Decode a `Request` into the matching `get`/`set`/`remove` call and map
+    /// the outcome to a `Response`, without printing anything — the CLI
+    /// frontends own presentation, this just serves the network frontend.
+    pub fn handle_request(&mut self, req: Request) -> Response {
+        match req {
+            Request::Get(key) => match self.get(key) {
+                Ok(Some(value)) => Response::Value(value),
+                Ok(None) => Response::NotFound,
+                Err(e) => Response::Err(e.to_string()),
+            },
+            Request::Set(key, value) => match self.set(key, value) {
+                Ok(()) => Response::Value(String::new()),
+                Err(e) => Response::Err(e.to_string()),
+            },
+            Request::Remove(key) => match self.remove(key) {
+                Ok(()) => Response::Value(String::new()),
+                Err(KvsError::KeyNotFound) => Response::NotFound,
+                Err(e) => Response::Err(e.to_string()),
+            },
+        }
+    }
+
+    /// Walk `gen_list` for on-disk generation sizes and the index for live
+    /// bytes, so operators can judge whether compaction is worthwhile.
+    pub fn stats(&self) -> Result<KvStoreStats> {
+        let gens = gen_list(&self.path)?;
+        let mut total_bytes = 0;
+        for gen in &gens {
+            total_bytes += metadata(log_path(&self.path, *gen))?.len();
+        }
+
+        let live_bytes: u64 = self.index.values().map(|meta| meta.len).sum();
+        let dead_bytes = total_bytes.saturating_sub(live_bytes);
+        let dead_ratio = if total_bytes == 0 {
+            0.0
+        } else {
+            dead_bytes as f64 / total_bytes as f64
+        };
+
+        Ok(KvStoreStats {
+            live_keys: self.index.len(),
+            generations: gens.len(),
+            total_bytes,
+            live_bytes,
+            dead_bytes,
+            dead_ratio,
+            lossy_generations: self.lossy_generations,
+        })
+    }
+
     fn log_cmd(&mut self, cmd: impl AsRef<str>) -> Result<()> {
         let cmd = format!("{}\n", cmd.as_ref());
         let bytes = cmd.as_bytes();
@@ -157,33 +365,34 @@ impl KvStore {
         self.writer.write_all(bytes)?;
         self.writer.flush()?;
         self.cursor += len;
+        self.readers
+            .insert(self.gen, gen_reader(&self.path, self.gen)?);
         Ok(())
     }
 
     pub fn compact(&mut self) -> Result<()> {
         self.gen += 2;
         self.cursor = 0;
-        self.writer = prepare_new_gen(&self.path, self.gen, &mut self.readers)?;
+        self.writer = prepare_new_gen(&self.path, self.gen)?;
 
         let compact_gen = self.gen - 1;
         let f = new_log(&self.path, compact_gen)?;
         let mut buf = BufWriter::new(f);
-        let mut cursor: u64 = 0;
-
-        self.readers
-            .insert(compact_gen, reader(&self.path, compact_gen)?);
+        buf.write_all(&[self.codec.to_byte()])?;
+        let mut cursor: u64 = 1;
 
-        for (_, IndexMeta { gen, pos }) in self.index.iter_mut() {
-            match self.readers.get_mut(&gen) {
+        for (_, IndexMeta { gen, pos, len }) in self.index.iter_mut() {
+            match self.readers.get(&gen) {
                 Some(reader) => {
-                    reader.seek(SeekFrom::Start(*pos))?;
-                    let mut cmd = String::new();
-                    reader.read_line(&mut cmd)?;
-                    let cmd = cmd.as_bytes();
-                    buf.write_all(cmd)?;
+                    let start = *pos as usize;
+                    let end = start + *len as usize;
+                    let raw = reader.codec.decompress(&reader.bytes()[start..end])?;
+                    let compressed = self.codec.compress(&raw)?;
+                    buf.write_all(&compressed)?;
                     *gen = compact_gen;
                     *pos = cursor;
-                    cursor += cmd.len() as u64;
+                    cursor += compressed.len() as u64;
+                    *len = compressed.len() as u64;
                 }
                 None => panic!(),
             }
@@ -191,10 +400,16 @@ impl KvStore {
 
         buf.flush()?;
 
+        write_hint(&self.path, compact_gen, &self.index)?;
+
+        self.readers
+            .insert(compact_gen, gen_reader(&self.path, compact_gen)?);
+
         for gen in gen_list(&self.path)? {
             if gen < compact_gen {
                 self.readers.remove(&gen);
                 remove_file(log_path(&self.path, gen))?;
+                let _ = remove_file(hint_path(&self.path, gen));
             }
         }
 
@@ -202,9 +417,8 @@ impl KvStore {
     }
 }
 
-fn prepare_new_gen(path: &Path, new_gen: Gen, readers: &mut KvsReaders) -> Result<BufWriter<File>> {
+fn prepare_new_gen(path: &Path, new_gen: Gen) -> Result<BufWriter<File>> {
     let log = new_log(path, new_gen)?;
-    readers.insert(new_gen, reader(path, new_gen)?);
 
     Ok(BufWriter::new(log))
 }
@@ -218,46 +432,161 @@ fn readers(path: &Path, gen_list: &[Gen]) -> Result<KvsReaders> {
     let mut readers: KvsReaders = HashMap::new();
 
     for gen in gen_list {
-        readers.insert(*gen, reader(&path, *gen)?);
+        readers.insert(*gen, gen_reader(&path, *gen)?);
     }
 
     Ok(readers)
 }
 
-fn reader(path: &Path, gen: Gen) -> Result<BufReader<File>> {
+/// Memory-map generation `gen`'s log read-only, so `get` can slice straight
+/// into it instead of seeking a `BufReader`, and work out which codec it was
+/// written with. Only a generation that `compact()` finalized has a `.hint`
+/// file and a matching 1-byte codec header at the start of its log; the
+/// still-growing active generation has neither and reads as `Codec::None`.
+/// Re-mapped after every write to the active generation and after
+/// compaction so newly written bytes become visible.
+fn gen_reader(path: &Path, gen: Gen) -> Result<GenReader> {
     let f = File::open(log_path(path, gen))?;
-    Ok(BufReader::new(f))
+    let mmap = if f.metadata()?.len() == 0 {
+        None
+    } else {
+        Some(unsafe { Mmap::map(&f)? })
+    };
+    let codec = match &mmap {
+        Some(mmap) if hint_path(path, gen).is_file() => Codec::from_byte(mmap[0]),
+        _ => Codec::None,
+    };
+
+    Ok(GenReader { mmap, codec })
 }
 
-fn index(path: &Path, gen_list: &[Gen]) -> Result<HashMap<KvsKey, IndexMeta>> {
-    let mut index: HashMap<KvsKey, IndexMeta> = HashMap::new();
+fn index(path: &Path, gen_list: &[Gen]) -> Result<(KvsIndex, usize)> {
+    let mut index: KvsIndex = HashMap::new();
+    let mut lossy_generations = 0;
+    let active_gen = gen_list.iter().max().copied();
     for gen in gen_list {
-        let f = File::open(log_path(path, *gen))?;
-        let mut buf = BufReader::new(f);
-        let mut pos = 0;
+        match index_from_hint(path, *gen) {
+            Ok(Some(entries)) => index.extend(entries),
+            _ => {
+                if index_from_log(path, *gen, Some(*gen) == active_gen, &mut index)? {
+                    lossy_generations += 1;
+                }
+            }
+        }
+    }
+
+    Ok((index, lossy_generations))
+}
 
-        loop {
-            let mut cmd = String::new();
-            let n = buf.read_line(&mut cmd)?;
+/// Rebuild the index for `gen` from its `.hint` file, or `Ok(None)` if it
+/// doesn't have one. Returns `Err` if a record's `pos`/`len` doesn't fit
+/// inside the generation's actual log, so the caller falls back to a full
+/// replay instead of trusting a corrupt or stale hint file.
+fn index_from_hint(path: &Path, gen: Gen) -> Result<Option<HashMap<KvsKey, IndexMeta>>> {
+    let hint_path = hint_path(path, gen);
+    if !hint_path.is_file() {
+        return Ok(None);
+    }
 
-            if n == 0 {
-                break;
-            }
+    let log_len = metadata(log_path(path, gen))?.len();
+    let f = File::open(hint_path)?;
+    let buf = BufReader::new(f);
+    let mut entries = HashMap::new();
 
-            match serde_json::from_str(&cmd)? {
-                KvsCommand::Set(key, _) => {
-                    index.insert(key, IndexMeta { gen: *gen, pos });
-                }
-                KvsCommand::Remove(key) => {
-                    index.remove(&key);
-                }
+    for line in buf.lines() {
+        let HintRecord { key, pos, len } = serde_json::from_str(&line?)?;
+        let end = pos.checked_add(len).ok_or(KvsError::Unexpected)?;
+        if end > log_len {
+            return Err(KvsError::Unexpected);
+        }
+        entries.insert(key, IndexMeta { gen, pos, len });
+    }
+
+    Ok(Some(entries))
+}
+
+/// Rebuild the index for `gen` by replaying its log from scratch. The log is
+/// plain JSON from byte 0 when `is_active` says `gen` is the still-growing
+/// generation, or when the log is empty or starts with `{` (an earlier
+/// session's active log); otherwise it is one `compact()` already finalized
+/// (a 1-byte codec header followed by records written with that codec,
+/// back to back). A finalized generation
+/// whose codec is anything but `Codec::None` can't be replayed without its
+/// `.hint` file — individual record lengths aren't recoverable from the
+/// compressed bytes alone — so we drop its keys from the index instead of
+/// failing `open` outright (the caller surfaces the drop via
+/// `KvStoreStats::lossy_generations`), returning `true` in that case.
+fn index_from_log(
+    path: &Path,
+    gen: Gen,
+    is_active: bool,
+    index: &mut HashMap<KvsKey, IndexMeta>,
+) -> Result<bool> {
+    let f = File::open(log_path(path, gen))?;
+    let mut reader = BufReader::new(f);
+
+    // `compact()` always writes a header byte, and it is never `{`.
+    let plain = is_active || matches!(reader.fill_buf()?.first().copied(), None | Some(b'{'));
+    let header_len = if plain {
+        0
+    } else {
+        let mut header = [0u8; 1];
+        if reader.read_exact(&mut header).is_err() || Codec::from_byte(header[0]) != Codec::None {
+            return Ok(true);
+        }
+        1
+    };
+
+    let mut stream = serde_json::Deserializer::from_reader(reader).into_iter::<KvsCommand>();
+    let mut pos = header_len + stream.byte_offset() as u64;
+
+    while let Some(cmd) = stream.next() {
+        let new_pos = header_len + stream.byte_offset() as u64;
+        let len = new_pos - pos;
+
+        match cmd? {
+            KvsCommand::Set(key, _) => {
+                index.insert(key, IndexMeta { gen, pos, len });
+            }
+            KvsCommand::Remove(key) => {
+                index.remove(&key);
             }
+        }
+
+        // In a plain log each record was written as `{json}\n` by `log_cmd`;
+        // byte_offset() stops right after the value, before that trailing
+        // separator, so skip it before using this offset as the next
+        // record's start. `compact()` writes no separator.
+        pos = if plain { new_pos + 1 } else { new_pos };
+    }
+
+    Ok(false)
+}
+
+fn hint_path(path: &Path, gen: Gen) -> PathBuf {
+    path.join(format!("{}.{}", gen, HINT_FILE_EXT))
+}
 
-            pos += n as u64;
+/// Write the `.hint` file for `gen`, recording the live position of every
+/// key the index currently resolves to that generation.
+fn write_hint(path: &Path, gen: Gen, index: &KvsIndex) -> Result<()> {
+    let f = File::create(hint_path(path, gen))?;
+    let mut buf = BufWriter::new(f);
+
+    for (key, meta) in index.iter() {
+        if meta.gen == gen {
+            let record = HintRecord {
+                key: key.clone(),
+                pos: meta.pos,
+                len: meta.len,
+            };
+            serde_json::to_writer(&mut buf, &record)?;
+            buf.write_all(b"\n")?;
         }
     }
 
-    Ok(index)
+    buf.flush()?;
+    Ok(())
 }
 
 fn log_path(path: &Path, gen: Gen) -> PathBuf {